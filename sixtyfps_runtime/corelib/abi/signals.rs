@@ -1,38 +1,125 @@
 /*!
-Signal that can be connected to  one sigle handler.
+Signal that can be connected to one or several handlers.
 
 TODO: reconsider if we should rename that to `Event`
 but then it should also be renamed everywhere, including in the language grammar
 */
 
 use super::properties::EvaluationContext;
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
 
-/// A Signal that can be connected to a handler.
+/// The state shared between a [`Signal`] and the [`Subscription`]s returned by [`Signal::connect`].
+#[derive(Default)]
+struct SignalState<Arg> {
+    /// FIXME: Box<dyn> is a fat object and we probaly want to put an erased type in there
+    handlers: core::cell::RefCell<BTreeMap<usize, Box<dyn Fn(&EvaluationContext, Arg)>>>,
+    /// The ids of the handlers currently being invoked from `emit`, innermost (most recently
+    /// pushed) last. A `Vec` rather than a single slot because a handler may itself call `emit`
+    /// on the same signal (e.g. indirectly through a binding it updates), nesting invocations.
+    invoking_stack: core::cell::RefCell<Vec<usize>>,
+    /// The ids, among those in `invoking_stack`, that were disconnected (their `Subscription`
+    /// dropped, or cleared by `set_handler`) while their handler was being invoked. Checked by
+    /// `emit` right after the id's frame returns, so it knows not to reinsert them, without
+    /// disturbing any outer, still-running frame for a different id.
+    cancelled: core::cell::RefCell<HashSet<usize>>,
+}
+
+/// A Signal that can be connected to one or several handlers.
 ///
 /// The Arg represents the argument. It should always be a tuple
 ///
 #[derive(Default)]
 #[repr(C)]
 pub struct Signal<Arg> {
-    /// FIXME: Box<dyn> is a fat object and we probaly want to put an erased type in there
-    handler: Option<Box<dyn Fn(&EvaluationContext, Arg)>>,
+    state: Rc<SignalState<Arg>>,
+    next_id: core::cell::Cell<usize>,
 }
 
 impl<Arg> Signal<Arg> {
     /// Emit the signal with the given argument.
     ///
     /// The constext must be a context corresponding to the component in which the signal is contained.
-    pub fn emit(&self, context: &EvaluationContext, a: Arg) {
-        if let Some(h) = &self.handler {
-            h(context, a);
+    ///
+    /// All the handlers connected at the time of the call are notified, each with a clone of
+    /// `a`. A handler that is connected by another handler while the signal is being emitted is
+    /// not notified by this call; one that is disconnected before its turn is skipped.
+    pub fn emit(&self, context: &EvaluationContext, a: Arg)
+    where
+        Arg: Clone,
+    {
+        let ids: Vec<usize> = self.state.handlers.borrow().keys().copied().collect();
+        for id in ids {
+            // Temporarily take the handler out of the map so that it may itself connect or
+            // disconnect handlers (including itself) on this signal without deadlocking on
+            // the RefCell. If the id is no longer present, it was disconnected by a handler
+            // that ran earlier in this emission, so skip it.
+            let handler = match self.state.handlers.borrow_mut().remove(&id) {
+                Some(h) => h,
+                None => continue,
+            };
+            self.state.invoking_stack.borrow_mut().push(id);
+            handler(context, a.clone());
+            self.state.invoking_stack.borrow_mut().pop();
+            // Only reinsert if the handler didn't disconnect itself while it ran; otherwise
+            // we'd resurrect a handler whose `Subscription` was just dropped. Removing (rather
+            // than just reading) `id` from `cancelled` also keeps that set from growing forever.
+            let was_cancelled = self.state.cancelled.borrow_mut().remove(&id);
+            if !was_cancelled {
+                self.state.handlers.borrow_mut().entry(id).or_insert(handler);
+            }
         }
     }
 
     /// Set an handler to be called when the signal is emited
     ///
-    /// There can only be one single handler per signal.
-    pub fn set_handler(&mut self, f: impl Fn(&EvaluationContext, Arg) + 'static) {
-        self.handler = Some(Box::new(f));
+    /// This removes all previously connected handlers (whether installed via `set_handler`
+    /// or [`Self::connect`]) and installs `f` as the sole handler.
+    pub fn set_handler(&self, f: impl Fn(&EvaluationContext, Arg) + 'static) {
+        // Allocate a fresh id, same as `connect`, rather than resetting `next_id`: any
+        // `Subscription`s returned by earlier `connect` calls may still be outstanding, and
+        // reusing their id would make dropping them disconnect whatever handler now occupies it.
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        // Handlers currently being invoked by `emit` are out of the map; mark them cancelled so
+        // that `emit` does not reinsert them once they return.
+        self.state.cancelled.borrow_mut().extend(self.state.invoking_stack.borrow().iter().copied());
+        let mut handlers = self.state.handlers.borrow_mut();
+        handlers.clear();
+        handlers.insert(id, Box::new(f));
+    }
+
+    /// Connects an additional handler to be called when the signal is emitted, without
+    /// removing any handler that's already connected.
+    ///
+    /// The returned [`Subscription`] must be kept alive for as long as the handler should
+    /// remain connected: dropping it disconnects `f` from this signal.
+    pub fn connect(&self, f: impl Fn(&EvaluationContext, Arg) + 'static) -> Subscription<Arg> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.state.handlers.borrow_mut().insert(id, Box::new(f));
+        Subscription { state: self.state.clone(), id }
+    }
+}
+
+/// A guard returned by [`Signal::connect`]. Disconnects the associated handler when dropped.
+pub struct Subscription<Arg> {
+    state: Rc<SignalState<Arg>>,
+    id: usize,
+}
+
+impl<Arg> Drop for Subscription<Arg> {
+    fn drop(&mut self) {
+        if self.state.handlers.borrow_mut().remove(&self.id).is_none() {
+            // The handler wasn't in the map: either `set_handler` already removed it, or some
+            // `emit` frame (maybe nested inside another, if a handler's own call re-emits the
+            // same signal) has taken it out to invoke it. In the latter case, tell that frame not
+            // to reinsert it afterwards, without touching any other id's frame further up or down
+            // the stack.
+            if self.state.invoking_stack.borrow().contains(&self.id) {
+                self.state.cancelled.borrow_mut().insert(self.id);
+            }
+        }
     }
 }
 
@@ -57,7 +144,7 @@ fn signal_simple_test() {
         fn compute_layout(self: Pin<&Self>, _: &crate::EvaluationContext) {}
     }
     use crate::abi::datastructures::ComponentVTable;
-    let mut c = Component::default();
+    let c = Component::default();
     c.clicked.set_handler(|c, ()| unsafe {
         (*(c.component.as_ptr() as *const Component)).pressed.set(true)
     });
@@ -72,6 +159,175 @@ fn signal_simple_test() {
     assert_eq!(c.pressed.get(), true);
 }
 
+#[test]
+fn signal_multi_handler_test() {
+    use std::pin::Pin;
+    #[derive(Default)]
+    struct Component {
+        count: core::cell::Cell<i32>,
+        clicked: Signal<()>,
+    }
+    impl crate::abi::datastructures::Component for Component {
+        fn visit_children_item(
+            self: Pin<&Self>,
+            _: isize,
+            _: crate::abi::datastructures::ItemVisitorRefMut,
+        ) {
+        }
+        fn layout_info(self: Pin<&Self>) -> crate::abi::datastructures::LayoutInfo {
+            unimplemented!()
+        }
+        fn compute_layout(self: Pin<&Self>, _: &crate::EvaluationContext) {}
+    }
+    use crate::abi::datastructures::ComponentVTable;
+    let c = Component::default();
+    let sub1 = c.clicked.connect(|c, ()| unsafe {
+        let c = &*(c.component.as_ptr() as *const Component);
+        c.count.set(c.count.get() + 1);
+    });
+    let sub2 = c.clicked.connect(|c, ()| unsafe {
+        let c = &*(c.component.as_ptr() as *const Component);
+        c.count.set(c.count.get() + 10);
+    });
+    let vtable = ComponentVTable::new::<Component>();
+    let ctx = super::properties::EvaluationContext::for_root_component(unsafe {
+        Pin::new_unchecked(vtable::VRef::from_raw(
+            core::ptr::NonNull::from(&vtable),
+            core::ptr::NonNull::from(&c).cast(),
+        ))
+    });
+
+    c.clicked.emit(&ctx, ());
+    assert_eq!(c.count.get(), 11);
+
+    drop(sub1);
+    c.clicked.emit(&ctx, ());
+    assert_eq!(c.count.get(), 21);
+
+    drop(sub2);
+    c.clicked.emit(&ctx, ());
+    assert_eq!(c.count.get(), 21);
+}
+
+#[test]
+fn signal_self_disconnect_test() {
+    use std::pin::Pin;
+    use std::rc::Rc;
+    #[derive(Default)]
+    struct Component {
+        count: core::cell::Cell<i32>,
+        clicked: Signal<()>,
+    }
+    impl crate::abi::datastructures::Component for Component {
+        fn visit_children_item(
+            self: Pin<&Self>,
+            _: isize,
+            _: crate::abi::datastructures::ItemVisitorRefMut,
+        ) {
+        }
+        fn layout_info(self: Pin<&Self>) -> crate::abi::datastructures::LayoutInfo {
+            unimplemented!()
+        }
+        fn compute_layout(self: Pin<&Self>, _: &crate::EvaluationContext) {}
+    }
+    use crate::abi::datastructures::ComponentVTable;
+    let c = Component::default();
+
+    // The handler drops its own `Subscription` the first time it is invoked, from within the
+    // call itself. It must not be resurrected by `emit` once the call returns, and must
+    // therefore never fire a second time.
+    let own_sub: Rc<core::cell::RefCell<Option<Subscription<()>>>> = Default::default();
+    let sub = c.clicked.connect({
+        let own_sub = own_sub.clone();
+        move |c, ()| unsafe {
+            let c = &*(c.component.as_ptr() as *const Component);
+            c.count.set(c.count.get() + 1);
+            own_sub.borrow_mut().take();
+        }
+    });
+    *own_sub.borrow_mut() = Some(sub);
+
+    let vtable = ComponentVTable::new::<Component>();
+    let ctx = super::properties::EvaluationContext::for_root_component(unsafe {
+        Pin::new_unchecked(vtable::VRef::from_raw(
+            core::ptr::NonNull::from(&vtable),
+            core::ptr::NonNull::from(&c).cast(),
+        ))
+    });
+
+    c.clicked.emit(&ctx, ());
+    assert_eq!(c.count.get(), 1);
+    c.clicked.emit(&ctx, ());
+    assert_eq!(c.count.get(), 1);
+}
+
+#[test]
+fn signal_reentrant_self_disconnect_test() {
+    use std::pin::Pin;
+    use std::rc::Rc;
+    #[derive(Default)]
+    struct Component {
+        count: core::cell::Cell<i32>,
+        clicked: Signal<()>,
+    }
+    impl crate::abi::datastructures::Component for Component {
+        fn visit_children_item(
+            self: Pin<&Self>,
+            _: isize,
+            _: crate::abi::datastructures::ItemVisitorRefMut,
+        ) {
+        }
+        fn layout_info(self: Pin<&Self>) -> crate::abi::datastructures::LayoutInfo {
+            unimplemented!()
+        }
+        fn compute_layout(self: Pin<&Self>, _: &crate::EvaluationContext) {}
+    }
+    use crate::abi::datastructures::ComponentVTable;
+    let c = Component::default();
+
+    let vtable = ComponentVTable::new::<Component>();
+    let ctx = super::properties::EvaluationContext::for_root_component(unsafe {
+        Pin::new_unchecked(vtable::VRef::from_raw(
+            core::ptr::NonNull::from(&vtable),
+            core::ptr::NonNull::from(&c).cast(),
+        ))
+    });
+
+    // `outer` (connected first, so invoked first) re-emits the same signal from within its own
+    // call, and while that nested `emit` is running, `inner` (connected second) disconnects
+    // itself. The nested call's bookkeeping must not clobber `outer`'s still-in-flight state:
+    // `outer` never disconnects itself and must keep firing on every subsequent emission.
+    let reentered = Rc::new(core::cell::Cell::new(false));
+    let reentered2 = reentered.clone();
+    let _sub_outer = c.clicked.connect(move |ctx, ()| unsafe {
+        let comp = &*(ctx.component.as_ptr() as *const Component);
+        comp.count.set(comp.count.get() + 100);
+        if !reentered2.get() {
+            reentered2.set(true);
+            comp.clicked.emit(ctx, ());
+        }
+    });
+    let inner_sub: Rc<core::cell::RefCell<Option<Subscription<()>>>> = Default::default();
+    let inner_sub2 = inner_sub.clone();
+    let sub_inner = c.clicked.connect(move |ctx, ()| unsafe {
+        let comp = &*(ctx.component.as_ptr() as *const Component);
+        comp.count.set(comp.count.get() + 1);
+        inner_sub2.borrow_mut().take();
+    });
+    *inner_sub.borrow_mut() = Some(sub_inner);
+
+    c.clicked.emit(&ctx, ());
+    // First round: `outer` (+100) fires and re-emits; `outer` is out of the map while it runs,
+    // so the nested call only invokes `inner` (+1). The outer call then skips `inner`, which
+    // disconnected itself.
+    assert_eq!(c.count.get(), 101);
+
+    c.clicked.emit(&ctx, ());
+    // `inner` disconnected itself during the first round and must stay gone; `outer` was never
+    // disconnecting itself and must still be present and fire normally (+100).
+    assert_eq!(c.count.get(), 201);
+}
+
 #[allow(non_camel_case_types)]
 type c_void = ();
 #[repr(C)]
@@ -131,6 +387,56 @@ pub unsafe extern "C" fn sixtyfps_signal_set_handler(
     sig.set_handler(real_binding);
 }
 
+/// An opaque handle to a [`Subscription`] obtained from [`sixtyfps_signal_connect`], to be
+/// passed to [`sixtyfps_signal_disconnect`] in order to disconnect the handler.
+#[repr(C)]
+pub struct SignalSubscriptionOpaque(*const c_void, usize);
+
+static_assertions::assert_eq_align!(SignalSubscriptionOpaque, Subscription<()>);
+static_assertions::assert_eq_size!(SignalSubscriptionOpaque, Subscription<()>);
+
+/// Connects an additional handler to the signal, without removing any handler that's already
+/// connected. The returned subscription handle must later be passed to
+/// [`sixtyfps_signal_disconnect`] exactly once, or leaked intentionally to keep the handler
+/// connected forever.
+///
+/// The binding has signature fn(user_data, context)
+#[no_mangle]
+pub unsafe extern "C" fn sixtyfps_signal_connect(
+    sig: *const SignalOpaque,
+    binding: extern "C" fn(*mut c_void, &EvaluationContext),
+    user_data: *mut c_void,
+    drop_user_data: Option<extern "C" fn(*mut c_void)>,
+    out: *mut SignalSubscriptionOpaque,
+) {
+    let sig = &*(sig as *const Signal<()>);
+
+    struct UserData {
+        user_data: *mut c_void,
+        drop_user_data: Option<extern "C" fn(*mut c_void)>,
+    }
+
+    impl Drop for UserData {
+        fn drop(&mut self) {
+            if let Some(x) = self.drop_user_data {
+                x(self.user_data)
+            }
+        }
+    }
+    let ud = UserData { user_data, drop_user_data };
+
+    let real_binding = move |compo: &EvaluationContext, ()| {
+        binding(ud.user_data, compo);
+    };
+    core::ptr::write(out as *mut Subscription<()>, sig.connect(real_binding));
+}
+
+/// Disconnects a handler previously connected with [`sixtyfps_signal_connect`].
+#[no_mangle]
+pub unsafe extern "C" fn sixtyfps_signal_disconnect(subscription: *mut SignalSubscriptionOpaque) {
+    core::ptr::read(subscription as *mut Subscription<()>);
+}
+
 /// Destroy signal
 #[no_mangle]
 pub unsafe extern "C" fn sixtyfps_signal_drop(handle: *mut SignalOpaque) {
@@ -14,10 +14,80 @@ use crate::component::ComponentRc;
 use crate::graphics::Point;
 use crate::input::{KeyEvent, MouseEventType};
 use crate::items::{ItemRc, ItemRef};
+use crate::signals::Signal;
 use crate::slice::Slice;
 use core::pin::Pin;
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use std::rc::Rc;
 
+/// An opaque, `Copy`-able token identifying a focusable item, in the style of gpui's `FocusId`.
+/// Carried as the argument of the [`GenericWindow`] focus signals so that a handler can tell
+/// which item gained or lost focus without holding a reference to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FocusHandle(usize);
+
+impl FocusHandle {
+    /// Creates a new, unique focus handle.
+    ///
+    /// Every item that can receive the focus needs a handle that stays the same for as long as
+    /// the item is alive, so that a [`GenericWindow::focus_gained_signal`] /
+    /// [`GenericWindow::focus_lost_signal`] handler can tell which item a notification is about.
+    /// Callers are expected to mint one of these the first time they see a given item (typically
+    /// from a [`GenericWindow::focus_handle_for_item`] implementation) and cache it rather than
+    /// calling this again for the same item.
+    pub fn new() -> Self {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for FocusHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Represents an event sent by the windowing system when the input method editor changes the
+/// composition state of the text being entered, mirroring winit's `Ime` event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImeEvent {
+    /// The text currently being composed, and not yet committed, together with an optional
+    /// cursor range (in bytes) into that text that the IME wants to highlight.
+    Preedit { text: String, cursor: Option<(usize, usize)> },
+    /// The IME has finished composing and the given text should be appended to the focused
+    /// item's content.
+    Commit(String),
+}
+
+/// The shape of the mouse cursor that an item may request to be shown while the pointer
+/// hovers over it, such as an I-beam over a text field or a resize arrow over a splitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseCursor {
+    /// The platform-specific default cursor.
+    Default,
+    /// Indicates that text can be selected, typically an I-beam.
+    Text,
+    /// Indicates that the item is clickable, typically a hand.
+    Pointer,
+    /// A crosshair cursor, typically used for precise selection.
+    Crosshair,
+    /// Indicates that the item can be resized horizontally.
+    ColResize,
+    /// Indicates that the item can be resized vertically.
+    RowResize,
+    /// Indicates that the item can be dragged, in its resting (not currently grabbed) state.
+    Grab,
+    /// Indicates that the item is currently being dragged.
+    Grabbing,
+}
+
+impl Default for MouseCursor {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
 /// This trait represents the interface that the generated code and the run-time
 /// require in order to implement functionality such as device-independent pixels,
 /// window resizing and other typicaly windowing system related tasks.
@@ -37,11 +107,50 @@ pub trait GenericWindow {
     /// * `pos`: The position of the mouse event in window physical coordinates.
     /// * `what`: The type of mouse event.
     /// * `component`: The SixtyFPS compiled component that provides the tree of items.
-    fn process_mouse_input(
-        self: Rc<Self>,
-        pos: winit::dpi::PhysicalPosition<f64>,
-        what: MouseEventType,
-    );
+    ///
+    /// As the pointer moves, the implementation is expected to look up the `mouse_cursor`
+    /// property of the hovered item and call [`Self::update_hovered_mouse_cursor`] so that
+    /// [`Self::set_mouse_cursor`] is issued only when the cursor actually changes.
+    ///
+    /// The position is expressed with [`crate::graphics::Point`] rather than a winit type so
+    /// that backends not built on winit (such as an embedded [`Self::map_embedded`] window) can
+    /// feed input too.
+    fn process_mouse_input(self: Rc<Self>, pos: Point, what: MouseEventType);
+    /// Returns the [`MouseCursor`] requested by the `mouse_cursor` property of the item
+    /// currently under the mouse pointer, or [`MouseCursor::Default`] if no item is hovered.
+    /// Implementations derive this from the same hit-test they perform in
+    /// [`Self::process_mouse_input`]. The default implementation always returns
+    /// [`MouseCursor::Default`].
+    fn hovered_item_mouse_cursor(&self) -> MouseCursor {
+        MouseCursor::default()
+    }
+    /// Calls [`Self::set_mouse_cursor`] with [`Self::hovered_item_mouse_cursor`]'s result, but
+    /// only if it differs from the cursor last applied through this method, so that moving the
+    /// mouse over an item doesn't issue a platform call on every single move event.
+    ///
+    /// [`Self::process_mouse_input`] implementations should call this once they've updated
+    /// whatever hit-test state `hovered_item_mouse_cursor` reads from.
+    fn update_hovered_mouse_cursor(&self) {
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+        thread_local! {
+            static LAST_CURSOR: RefCell<HashMap<usize, MouseCursor>> = RefCell::new(HashMap::new());
+        }
+        let key = self as *const Self as *const u8 as usize;
+        let cursor = self.hovered_item_mouse_cursor();
+        let changed = LAST_CURSOR.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if cache.get(&key) == Some(&cursor) {
+                false
+            } else {
+                cache.insert(key, cursor);
+                true
+            }
+        });
+        if changed {
+            self.set_mouse_cursor(cursor);
+        }
+    }
     /// Receive a key event and pass it to the items of the component to
     /// change their state.
     ///
@@ -49,9 +158,64 @@ pub trait GenericWindow {
     /// * `event`: The key event received by the windowing system.
     /// * `component`: The SixtyFPS compiled component that provides the tree of items.
     fn process_key_input(self: Rc<Self>, event: &KeyEvent);
+    /// Receive an IME composition event (pre-edit text or a commit) and forward it to the
+    /// currently focused item, so that e.g. CJK or dead-key input can be rendered and applied.
+    /// The default implementation routes `event` to [`Self::focused_item`] via
+    /// [`Self::dispatch_ime_event`] and does nothing if no item currently has the focus.
+    ///
+    /// Arguments:
+    /// * `event`: The IME event received by the windowing system.
+    fn process_ime_event(self: Rc<Self>, event: ImeEvent) {
+        if let Some(focus_item) = self.focused_item() {
+            self.dispatch_ime_event(&focus_item, event);
+        }
+    }
+    /// Returns the item that currently has the keyboard focus, as most recently set by
+    /// [`Self::set_focus_item`], or `None` if no item is focused.
+    fn focused_item(&self) -> Option<ItemRc>;
+    /// Applies a routed [`ImeEvent`] to `item`, e.g. updating its pre-edit text or appending a
+    /// committed string to its content. Called by the default [`Self::process_ime_event`].
+    fn dispatch_ime_event(&self, item: &ItemRc, event: ImeEvent);
+    /// Toggles whether the input method editor should be allowed to compose text for this
+    /// window. This is typically enabled while a text input item has the focus and disabled
+    /// otherwise. The default implementation forwards to the platform window via
+    /// [`Self::with_platform_window`].
+    fn set_ime_allowed(&self, allowed: bool) {
+        self.with_platform_window(&mut |window| window.set_ime_allowed(allowed));
+    }
+    /// Returns whether `item` accepts text input and should therefore have IME composition
+    /// enabled while it holds the keyboard focus.
+    fn item_accepts_ime(&self, item: &ItemRc) -> bool;
+    /// Enables or disables IME composition, via [`Self::set_ime_allowed`], depending on whether
+    /// `focus_item` accepts text input according to [`Self::item_accepts_ime`]; disables it if
+    /// `focus_item` is `None`. [`Self::set_focus_item`] implementations call this once they've
+    /// stored the new focus item, so text entry items get IME composition turned on and off
+    /// automatically as the focus moves on or off them.
+    fn update_ime_allowed_for_focus(&self, focus_item: Option<&ItemRc>) {
+        let enabled = focus_item.map_or(false, |item| self.item_accepts_ime(item));
+        self.set_ime_allowed(enabled);
+    }
+    /// Tells the windowing system where to place the IME candidate window, in window physical
+    /// coordinates. This is typically set to the position of the text cursor of the focused item.
+    /// The default implementation forwards to the platform window via
+    /// [`Self::with_platform_window`].
+    fn set_ime_position(&self, pos: Point) {
+        self.with_platform_window(&mut |window| {
+            window.set_ime_position(winit::dpi::PhysicalPosition::new(pos.x, pos.y));
+        });
+    }
     /// Calls the `callback` function with the underlying winit::Window that this
     /// GenericWindow backs.
     fn with_platform_window(&self, callback: &mut dyn FnMut(&winit::window::Window));
+    /// Returns a [`RawWindowHandle`] for the native window that backs this `GenericWindow`,
+    /// so that it can be embedded into, or act as the parent of, a window owned by a host
+    /// that isn't built on winit. The default implementation obtains it from the platform
+    /// window via [`Self::with_platform_window`].
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let mut handle = None;
+        self.with_platform_window(&mut |window| handle = Some(window.raw_window_handle()));
+        handle.expect("with_platform_window must invoke the callback synchronously")
+    }
     /// Requests for the window to be mapped to the screen.
     ///
     /// Arguments:
@@ -62,16 +226,78 @@ pub trait GenericWindow {
     ///   for the initial size of the window. Then bindings are installed on these properties to keep them up-to-date
     ///   with the size as it may be changed by the user or the windowing system in general.
     fn map_window(self: Rc<Self>, event_loop: &crate::eventloop::EventLoop);
+    /// Renders and drives input for this window inside a surface owned by `parent`, instead of
+    /// spinning our own [`crate::eventloop::EventLoop`]. This is the embedding path used to host
+    /// a SixtyFPS component inside a host-provided window, such as an audio-plugin editor.
+    ///
+    /// Arguments:
+    /// * `parent`: A handle to the native window that should act as the parent surface.
+    /// * `size`: The initial logical size to render at.
+    ///
+    /// The host is then responsible for forwarding draw and input callbacks itself, as there is
+    /// no event loop to dispatch them.
+    ///
+    /// The default implementation panics, since hosting inside a foreign surface is inherently
+    /// platform-specific; backends that support it must override this method.
+    fn map_embedded(self: Rc<Self>, _parent: RawWindowHandle, _size: crate::graphics::Size) {
+        unimplemented!(
+            "this window backend does not support embedding into a host-provided window"
+        )
+    }
     /// Removes the window from the screen. The window is not destroyed though, it can be show (mapped) again later
     /// by calling [`GenericWindow::map_window`].
     fn unmap_window(self: Rc<Self>);
     /// Issue a request to the windowing system to re-render the contents of the window. This is typically an asynchronous
     /// request.
     fn request_redraw(&self);
+    /// Sets the shape of the mouse cursor shown over this window, forwarding to the underlying
+    /// `winit::window::Window::set_cursor_icon`.
+    fn set_mouse_cursor(&self, cursor: MouseCursor) {
+        let icon = match cursor {
+            MouseCursor::Default => winit::window::CursorIcon::Default,
+            MouseCursor::Text => winit::window::CursorIcon::Text,
+            MouseCursor::Pointer => winit::window::CursorIcon::Hand,
+            MouseCursor::Crosshair => winit::window::CursorIcon::Crosshair,
+            MouseCursor::ColResize => winit::window::CursorIcon::ColResize,
+            MouseCursor::RowResize => winit::window::CursorIcon::RowResize,
+            MouseCursor::Grab => winit::window::CursorIcon::Grab,
+            MouseCursor::Grabbing => winit::window::CursorIcon::Grabbing,
+        };
+        self.with_platform_window(&mut |window| window.set_cursor_icon(icon));
+    }
     /// Returns the scale factor set on the window, as provided by the windowing system.
     fn scale_factor(&self) -> f32;
     /// Sets an overriding scale factor for the window. This is typically only used for testing.
     fn set_scale_factor(&self, factor: f32);
+    /// Called by the event loop when the windowing system reports that the scale factor of the
+    /// window changed, typically because the user dragged it to a monitor with a different DPI.
+    ///
+    /// Arguments:
+    /// * `new_factor`: The new scale factor reported by the windowing system.
+    /// * `new_physical_size`: The new physical size of the window, in the same event.
+    ///
+    /// Implementations are expected to store `new_factor` (as returned afterwards by
+    /// [`Self::scale_factor`]), convert `new_physical_size` to logical device-independent pixels
+    /// using it, and push the result back through [`Self::set_width`] and [`Self::set_height`] so
+    /// that any property binding expressed in logical pixels re-evaluates against the new scale
+    /// factor. [`Self::get_geometry`] reflects the updated logical geometry once this returns.
+    ///
+    /// Because the resulting resize can itself trigger a redraw request while this call is
+    /// still resizing the back-buffer, implementations must coalesce redraw requests raised
+    /// during this call so that at most one [`Self::request_redraw`] is issued per scale factor
+    /// change. The default implementation does not coalesce: it calls [`Self::request_redraw`]
+    /// once itself, in addition to any redraw that `set_width`/`set_height` request.
+    fn process_scale_factor_change(self: Rc<Self>, new_factor: f32, new_physical_size: (f32, f32)) {
+        self.set_scale_factor(new_factor);
+        let logical_width = new_physical_size.0 / new_factor;
+        let logical_height = new_physical_size.1 / new_factor;
+        self.set_width(logical_width);
+        self.set_height(logical_height);
+        // `set_width`/`set_height` may themselves schedule a redraw as dpi-dependent bindings
+        // re-evaluate; issue one more here so a redraw is requested even if they don't. Redraws
+        // they requested are not merged with this one.
+        self.request_redraw();
+    }
     /// Sets the size of the window to the specified `width`. This method is typically called in response to receiving a
     /// window resize event from the windowing system.
     fn set_width(&self, width: f32);
@@ -94,12 +320,56 @@ pub trait GenericWindow {
     fn set_current_keyboard_modifiers(&self, modifiers: crate::input::KeyboardModifiers);
 
     /// Sets the focus to the item pointed to by item_ptr. This will remove the focus from any
-    /// currently focused item.
+    /// currently focused item. Implementations should store the new focus item (so that
+    /// [`Self::focused_item`] reflects it) and call [`Self::update_ime_allowed_for_focus`] to
+    /// enable or disable IME composition as appropriate for the newly focused item.
+    ///
+    /// If the newly focused item differs from the previously focused one, implementations must
+    /// call [`Self::notify_focus_change`] with the previously and newly focused item, which emits
+    /// [`Self::focus_lost_signal`] for the previous item followed by [`Self::focus_gained_signal`]
+    /// for the new one, in that order. Re-focusing the item that already has the focus emits
+    /// nothing.
     fn set_focus_item(self: Rc<Self>, focus_item: &ItemRc);
     /// Sets the focus on the window to true or false, depending on the have_focus argument.
     /// This results in WindowFocusReceived and WindowFocusLost events.
     fn set_focus(self: Rc<Self>, have_focus: bool);
 
+    /// Returns a [`FocusHandle`] for `item` that stays the same across calls for as long as
+    /// `item` is alive, minting and caching a new one via [`FocusHandle::new`] the first time a
+    /// given item is seen.
+    fn focus_handle_for_item(&self, item: &ItemRc) -> FocusHandle;
+    /// Emits [`Self::focus_lost_signal`] for `previous` and then [`Self::focus_gained_signal`]
+    /// for `next`, resolving each to its [`FocusHandle`] via [`Self::focus_handle_for_item`] and
+    /// skipping both if they resolve to the same handle (i.e. the focus didn't actually move).
+    /// [`Self::set_focus_item`] implementations call this once they've stored the new focus item.
+    fn notify_focus_change(
+        &self,
+        context: &crate::properties::EvaluationContext,
+        previous: Option<&ItemRc>,
+        next: Option<&ItemRc>,
+    ) {
+        let previous_handle = previous.map(|item| self.focus_handle_for_item(item));
+        let next_handle = next.map(|item| self.focus_handle_for_item(item));
+        if previous_handle == next_handle {
+            return;
+        }
+        if let Some(handle) = previous_handle {
+            self.focus_lost_signal().emit(context, handle);
+        }
+        if let Some(handle) = next_handle {
+            self.focus_gained_signal().emit(context, handle);
+        }
+    }
+
+    /// The signal emitted when an item gains the keyboard focus, whether because
+    /// [`Self::set_focus_item`] changed the active item or [`Self::set_focus`] brought the window
+    /// back into activation.
+    fn focus_gained_signal(&self) -> &Signal<FocusHandle>;
+    /// The signal emitted when an item loses the keyboard focus, whether because
+    /// [`Self::set_focus_item`] changed the active item or [`Self::set_focus`] took activation
+    /// away from the window.
+    fn focus_lost_signal(&self) -> &Signal<FocusHandle>;
+
     /// Show a popup at the given position
     fn show_popup(&self, popup: &ComponentRc, position: Point);
     /// Close the active popup if any
@@ -118,6 +388,22 @@ impl ComponentWindow {
     pub fn new(window_impl: std::rc::Rc<dyn crate::eventloop::GenericWindow>) -> Self {
         Self(window_impl)
     }
+
+    /// Creates a new instance of a ComponentWindow that renders into, and receives input from, a
+    /// host-owned surface rather than spinning its own event loop. Use this to host a SixtyFPS
+    /// component inside a parent-provided window, such as an audio-plugin editor.
+    ///
+    /// Drawing and mouse input are then driven by the host via [`Self::draw`] and
+    /// [`Self::process_mouse_input`]. `ComponentWindow` has no public entry point for key input.
+    pub fn new_embedded(
+        window_impl: std::rc::Rc<dyn crate::eventloop::GenericWindow>,
+        parent: RawWindowHandle,
+        size: crate::graphics::Size,
+    ) -> Self {
+        window_impl.clone().map_embedded(parent, size);
+        Self(window_impl)
+    }
+
     /// Spins an event loop and renders the items of the provided component in this window.
     pub fn run(&self) {
         let event_loop = crate::eventloop::EventLoop::new();
@@ -129,6 +415,18 @@ impl ComponentWindow {
         self.0.clone().unmap_window();
     }
 
+    /// Draw the items of the associated component into this window. Used by embedders that
+    /// drive rendering themselves via [`Self::new_embedded`].
+    pub fn draw(&self) {
+        self.0.clone().draw()
+    }
+
+    /// Receive a mouse event and pass it to the items of the associated component. Used by
+    /// embedders that drive input themselves via [`Self::new_embedded`].
+    pub fn process_mouse_input(&self, pos: Point, what: MouseEventType) {
+        self.0.clone().process_mouse_input(pos, what)
+    }
+
     /// Returns the scale factor set on the window.
     pub fn scale_factor(&self) -> f32 {
         self.0.scale_factor()
@@ -139,6 +437,12 @@ impl ComponentWindow {
         self.0.set_scale_factor(factor)
     }
 
+    /// Called by the event loop implementation when the windowing system reports a scale
+    /// factor change, so that logical geometry and property bindings can be kept up-to-date.
+    pub(crate) fn process_scale_factor_change(&self, new_factor: f32, new_physical_size: (f32, f32)) {
+        self.0.clone().process_scale_factor_change(new_factor, new_physical_size)
+    }
+
     /// This function is called by the generated code when a component and therefore its tree of items are destroyed. The
     /// implementation typically uses this to free the underlying graphics resources cached via [RenderingCache][`crate::graphics::RenderingCache`].
     pub fn free_graphics_resources<'a>(&self, items: &Slice<'a, Pin<ItemRef<'a>>>) {
@@ -168,12 +472,54 @@ impl ComponentWindow {
         self.0.clone().process_key_input(event)
     }
 
+    pub(crate) fn process_ime_event(&self, event: ImeEvent) {
+        self.0.clone().process_ime_event(event)
+    }
+
+    /// Toggles whether the input method editor should be allowed to compose text for this window.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.0.set_ime_allowed(allowed)
+    }
+
+    /// Tells the windowing system where to place the IME candidate window, typically at the
+    /// position of the text cursor of the focused item.
+    pub fn set_ime_position(&self, pos: Point) {
+        self.0.set_ime_position(pos)
+    }
+
+    /// Overrides the shape of the mouse cursor shown over this window.
+    pub fn set_mouse_cursor(&self, cursor: MouseCursor) {
+        self.0.set_mouse_cursor(cursor)
+    }
+
     /// Clears the focus on any previously focused item and makes the provided
     /// item the focus item, in order to receive future key events.
     pub fn set_focus_item(&self, focus_item: &ItemRc) {
         self.0.clone().set_focus_item(focus_item)
     }
 
+    /// Connects a handler to be notified whenever an item gains the keyboard focus. The
+    /// returned [`crate::signals::Subscription`] must be kept alive for as long as the handler
+    /// should remain connected. This is also the signal that generated code binds `.slint`
+    /// declared focus callbacks to.
+    pub fn on_focus_gained(
+        &self,
+        f: impl Fn(&crate::properties::EvaluationContext, FocusHandle) + 'static,
+    ) -> crate::signals::Subscription<FocusHandle> {
+        self.0.focus_gained_signal().connect(f)
+    }
+
+    /// Connects a handler to be notified whenever an item loses the keyboard focus. The
+    /// returned [`crate::signals::Subscription`] must be kept alive for as long as the handler
+    /// should remain connected. This is also the signal that generated code binds `.slint`
+    /// declared focus callbacks to.
+    pub fn on_focus_lost(
+        &self,
+        f: impl Fn(&crate::properties::EvaluationContext, FocusHandle) + 'static,
+    ) -> crate::signals::Subscription<FocusHandle> {
+        self.0.focus_lost_signal().connect(f)
+    }
+
     /// Associates this window with the specified component, for future event handling, etc.
     pub fn set_component(&self, component: &ComponentRc) {
         self.0.clone().set_component(component)